@@ -44,6 +44,16 @@ pub enum Field {
     InterplacedBT
 }
 
+/// Capture method used to move frames from the driver to the application.
+#[derive(Copy, PartialEq, Eq)]
+pub enum IoMethod {
+    /// Streaming I/O with memory-mapped buffers (`REQBUFS` + `QBUF`/`DQBUF`).
+    Mmap,
+    /// Plain blocking `read()` into an internally owned buffer. Use this for
+    /// devices that advertise `READWRITE` but not `STREAMING`.
+    Read
+}
+
 #[derive(Copy)]
 pub struct Config<'a> {
     /**
@@ -70,7 +80,24 @@ pub struct Config<'a> {
      * Number of buffers in the queue of camera.
      * Default is `2`.
      */
-    pub nbuffers: u32
+    pub nbuffers: u32,
+    /**
+     * Capture method: memory-mapped streaming or blocking `read()`.
+     * Default is `IoMethod::Mmap`.
+     */
+    pub io: IoMethod,
+    /**
+     * Open the device in non-blocking mode. `capture()` then fails with an
+     * error of kind `ResourceUnavailable` when no frame is ready; use `poll()`
+     * to wait. Default is `false`.
+     */
+    pub nonblocking: bool,
+    /**
+     * Video standard (e.g. a PAL/NTSC bitmask) to select with `VIDIOC_S_STD`
+     * on `start()`. Needed for analog inputs on capture cards and tuners.
+     * `0` leaves the driver's default untouched.
+     */
+    pub standard: u64
 }
 
 impl<'a> default::Default for Config<'a> {
@@ -80,11 +107,195 @@ impl<'a> default::Default for Config<'a> {
             resolution: (640, 480),
             format: b"YUYV",
             field: Field::None,
-            nbuffers: 2
+            nbuffers: 2,
+            io: IoMethod::Mmap,
+            nonblocking: false,
+            standard: 0
         }
     }
 }
 
+fn buf_to_string(buf: &[u8]) -> String {
+    unsafe { String::from_raw_buf(buf.as_ptr()) }
+}
+
+/// A set of device capability flags reported by `VIDIOC_QUERYCAP`.
+#[derive(Copy, PartialEq, Eq)]
+pub struct CapabilityFlags {
+    bits: u32
+}
+
+impl CapabilityFlags {
+    /// Whether the device supports the single-plane video capture interface.
+    pub fn video_capture(&self) -> bool {
+        self.bits & v4l2::CAP_VIDEO_CAPTURE != 0
+    }
+
+    /// Whether the device supports the video output interface.
+    pub fn video_output(&self) -> bool {
+        self.bits & v4l2::CAP_VIDEO_OUTPUT != 0
+    }
+
+    /// Whether the device supports the video overlay interface.
+    pub fn video_overlay(&self) -> bool {
+        self.bits & v4l2::CAP_VIDEO_OVERLAY != 0
+    }
+
+    /// Whether the device can capture via the `read()` system call.
+    pub fn readwrite(&self) -> bool {
+        self.bits & v4l2::CAP_READWRITE != 0
+    }
+
+    /// Whether the device supports the streaming (mmap) I/O method.
+    pub fn streaming(&self) -> bool {
+        self.bits & v4l2::CAP_STREAMING != 0
+    }
+
+    /// The raw capability bitmask.
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+}
+
+impl fmt::Show for CapabilityFlags {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut names = vec![];
+        if self.video_capture() { names.push("VIDEO_CAPTURE"); }
+        if self.video_output()  { names.push("VIDEO_OUTPUT"); }
+        if self.video_overlay() { names.push("VIDEO_OVERLAY"); }
+        if self.readwrite()     { names.push("READWRITE"); }
+        if self.streaming()     { names.push("STREAMING"); }
+        write!(f, "{}", names.connect("|"))
+    }
+}
+
+/// Information about a device, as returned by `Camera::capabilities()`.
+pub struct Capabilities {
+    /// Name of the driver module (e.g. `"uvcvideo"`).
+    pub driver: String,
+    /// Name of the card (e.g. `"UVC Camera"`).
+    pub card: String,
+    /// Location of the device in the system (e.g. `"usb-0000:00:1a.0-1.2"`).
+    pub bus: String,
+    /// Kernel version as `(major, minor, patch)`.
+    pub version: (u8, u8, u8),
+    /// Capabilities available through the device node.
+    pub flags: CapabilityFlags
+}
+
+impl fmt::Show for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({}; {})", self.card, self.driver, self.bus)
+    }
+}
+
+/// Type of a video input, as reported by `VIDIOC_ENUMINPUT`.
+#[derive(Show, Copy, PartialEq, Eq)]
+pub enum InputType {
+    /// An RF-demodulated (tuner) input.
+    Tuner,
+    /// A direct analog or digital video input (composite, S-Video, camera).
+    Camera,
+    /// An input type unknown to this crate.
+    Unknown
+}
+
+impl InputType {
+    fn from_raw(itype: u32) -> InputType {
+        match itype {
+            v4l2::INPUT_TYPE_TUNER => InputType::Tuner,
+            v4l2::INPUT_TYPE_CAMERA => InputType::Camera,
+            _ => InputType::Unknown
+        }
+    }
+}
+
+/// Description of a single video input of the device.
+pub struct InputInfo {
+    /// Index, to be passed to `set_input`.
+    pub index: u32,
+    /// Human-readable name (e.g. `"Composite0"`).
+    pub name: String,
+    /// Type of the input.
+    pub itype: InputType,
+    /// Bitmask of video standards (PAL/NTSC/...) supported on this input.
+    pub standards: u64
+}
+
+impl fmt::Show for InputInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({})", self.name, self.itype)
+    }
+}
+
+/// Type of a user control, as reported by `VIDIOC_QUERYCTRL`.
+#[derive(Show, Copy, PartialEq, Eq)]
+pub enum ControlType {
+    Integer,
+    Boolean,
+    Menu,
+    Button,
+    Integer64,
+    CtrlClass,
+    String,
+    Bitmask,
+    IntegerMenu,
+    /// A control type unknown to this crate.
+    Unknown
+}
+
+impl ControlType {
+    fn from_raw(ctype: u32) -> ControlType {
+        match ctype {
+            v4l2::CTRL_TYPE_INTEGER => ControlType::Integer,
+            v4l2::CTRL_TYPE_BOOLEAN => ControlType::Boolean,
+            v4l2::CTRL_TYPE_MENU => ControlType::Menu,
+            v4l2::CTRL_TYPE_BUTTON => ControlType::Button,
+            v4l2::CTRL_TYPE_INTEGER64 => ControlType::Integer64,
+            v4l2::CTRL_TYPE_CTRL_CLASS => ControlType::CtrlClass,
+            v4l2::CTRL_TYPE_STRING => ControlType::String,
+            v4l2::CTRL_TYPE_BITMASK => ControlType::Bitmask,
+            v4l2::CTRL_TYPE_INTEGER_MENU => ControlType::IntegerMenu,
+            _ => ControlType::Unknown
+        }
+    }
+}
+
+/// One selectable entry of a menu-typed control.
+pub struct MenuItem {
+    pub index: u32,
+    /// Label of the entry, for `Menu` controls. Empty for `IntegerMenu`.
+    pub name: String,
+    /// Integer value of the entry, for `IntegerMenu` controls. `0` otherwise.
+    pub value: i64
+}
+
+/// Description of a single user control.
+pub struct ControlInfo {
+    /// Control id, to be passed to `get_control`/`set_control`.
+    pub id: u32,
+    /// Human-readable name (e.g. `"Brightness"`).
+    pub name: String,
+    /// Type of the control.
+    pub ctype: ControlType,
+    /// Minimum value.
+    pub minimum: i32,
+    /// Maximum value.
+    pub maximum: i32,
+    /// Step between valid values.
+    pub step: i32,
+    /// Default value.
+    pub default: i32,
+    /// Menu entries, for controls of type `Menu`/`IntegerMenu`.
+    pub menu: Vec<MenuItem>
+}
+
+impl fmt::Show for ControlInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({} [{}, {}])", self.name, self.ctype, self.minimum, self.maximum)
+    }
+}
+
 pub struct FormatInfo {
     /// FourCC of format (e.g. `b"H264"`).
     pub format: [u8; 4],
@@ -136,26 +347,110 @@ impl fmt::Show for FormatInfo {
     }
 }
 
+/// Resolutions a format supports: either a single size or a (possibly
+/// continuous) range. A continuous range is a stepwise range with step `1`.
+pub enum ResolutionInfo {
+    /// A single supported `(width, height)`.
+    Discrete(u32, u32),
+    /// A range of supported widths and heights.
+    Stepwise {
+        min_width: u32,
+        max_width: u32,
+        step_width: u32,
+        min_height: u32,
+        max_height: u32,
+        step_height: u32
+    }
+}
+
+impl fmt::Show for ResolutionInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ResolutionInfo::Discrete(w, h) => write!(f, "{}x{}", w, h),
+            ResolutionInfo::Stepwise { min_width: minw, max_width: maxw, step_width: sw,
+                                       min_height: minh, max_height: maxh, step_height: sh } =>
+                write!(f, "{}x{}..{}x{} (+{}x{})", minw, minh, maxw, maxh, sw, sh)
+        }
+    }
+}
+
+/// Frame intervals a mode supports: a single interval or a stepwise range.
+pub enum IntervalInfo {
+    /// A single supported `(numerator, denominator)` interval.
+    Discrete(u32, u32),
+    /// A range of supported intervals, each bound a `(numerator, denominator)`.
+    Stepwise {
+        min: (u32, u32),
+        max: (u32, u32),
+        step: (u32, u32)
+    }
+}
+
 pub struct ModeInfo {
-    pub resolution: (u32, u32),
-    pub intervals: Vec<(u32, u32)>
+    pub resolution: ResolutionInfo,
+    pub intervals: Vec<IntervalInfo>
 }
 
 impl ModeInfo {
-    pub fn new(resolution: (u32, u32)) -> ModeInfo {
+    pub fn new(resolution: ResolutionInfo) -> ModeInfo {
         ModeInfo {
             resolution: resolution,
             intervals: vec![]
         }
     }
+
+    /// Whether `(width, height)` falls on the resolution grid of this mode:
+    /// within `[min, max]` and reachable from `min` in whole steps.
+    pub fn contains_resolution(&self, width: u32, height: u32) -> bool {
+        match self.resolution {
+            ResolutionInfo::Discrete(w, h) => width == w && height == h,
+            ResolutionInfo::Stepwise { min_width: minw, max_width: maxw, step_width: sw,
+                                       min_height: minh, max_height: maxh, step_height: sh } =>
+                on_grid(width, minw, maxw, sw) && on_grid(height, minh, maxh, sh)
+        }
+    }
+
+    /// Whether `(numerator, denominator)` falls within the intervals of this
+    /// mode. Discrete modes accept only the listed intervals; stepwise and
+    /// continuous modes accept any interval between the `min` and `max` bounds.
+    ///
+    /// Intervals are fractions, so the bounds are compared as rationals rather
+    /// than gridding numerator and denominator separately (the fastest interval
+    /// has the larger denominator, so the two axes don't range together).
+    pub fn contains_interval(&self, interval: (u32, u32)) -> bool {
+        self.intervals.iter().any(|ival| match *ival {
+            IntervalInfo::Discrete(num, den) => interval == (num, den),
+            IntervalInfo::Stepwise { min, max, .. } => {
+                let (lo, hi) = if rational_le(min, max) { (min, max) } else { (max, min) };
+                rational_le(lo, interval) && rational_le(interval, hi)
+            }
+        })
+    }
+}
+
+/// Whether `a <= b` for the fractions `a` and `b`, by cross-multiplication.
+fn rational_le(a: (u32, u32), b: (u32, u32)) -> bool {
+    (a.0 as u64) * (b.1 as u64) <= (b.0 as u64) * (a.1 as u64)
 }
 
 impl fmt::Show for ModeInfo {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}x{}", self.resolution.0, self.resolution.1)
+        write!(f, "{}", self.resolution)
     }
 }
 
+fn on_grid(value: u32, min: u32, max: u32, step: u32) -> bool {
+    value >= min && value <= max && (step == 0 || (value - min) % step == 0)
+}
+
+/// How a `Frame` returns its storage to the driver when dropped.
+enum Release {
+    /// Re-enqueue the mmap buffer with `QBUF`.
+    Queue(v4l2::Buffer),
+    /// Nothing to do; the buffer is owned by the `Camera` (read mode).
+    Nothing
+}
+
 pub struct Frame<'a> {
     /// Slice of one of the buffers.
     pub data: &'a [u8],
@@ -164,17 +459,29 @@ pub struct Frame<'a> {
     /// FourCC of the format.
     pub format: [u8; 4],
     fd: int,
-    buffer: v4l2::Buffer
+    release: Release
 }
 
 #[unsafe_destructor]
 impl<'a> Drop for Frame<'a> {
     #[allow(unused_must_use)]
     fn drop(&mut self) {
-        v4l2::xioctl(self.fd, v4l2::VIDIOC_QBUF, &mut self.buffer);
+        if let Release::Queue(ref mut buffer) = self.release {
+            v4l2::xioctl(self.fd, v4l2::VIDIOC_QBUF, buffer);
+        }
     }
 }
 
+/// State needed to decode raw frames into the requested output format with
+/// libv4lconvert. The device is driven in mmap mode; each captured buffer is
+/// run through the converter into `buffer`.
+struct Conversion {
+    converter: v4l2::Converter,
+    src: v4l2::Format,
+    dest: v4l2::Format,
+    buffer: Vec<u8>
+}
+
 #[derive(Show, PartialEq)]
 enum State {
     Idle,
@@ -187,7 +494,10 @@ pub struct Camera<'a> {
     state: State,
     resolution: (u32, u32),
     format: [u8; 4],
-    buffers: Vec<&'a mut [u8]>
+    io: IoMethod,
+    buffers: Vec<&'a mut [u8]>,
+    readbuf: Vec<u8>,
+    conv: Option<Conversion>
 }
 
 impl<'a> Camera<'a> {
@@ -197,10 +507,161 @@ impl<'a> Camera<'a> {
             state: State::Idle,
             resolution: (0, 0),
             format: [0; 4],
-            buffers: vec![]
+            io: IoMethod::Mmap,
+            buffers: vec![],
+            readbuf: vec![],
+            conv: None
+        })
+    }
+
+    /// Query what the device supports via `VIDIOC_QUERYCAP`.
+    ///
+    /// Useful to check for `STREAMING` or `READWRITE` before calling `start()`,
+    /// which otherwise fails deep inside `tune_format`/`alloc_buffers`.
+    pub fn capabilities(&self) -> io::IoResult<Capabilities> {
+        let mut cap = v4l2::Capability::new();
+
+        try!(v4l2::xioctl(self.fd, v4l2::VIDIOC_QUERYCAP, &mut cap));
+
+        // `device_caps` is only meaningful when the driver advertises it.
+        let flags = if cap.capabilities & v4l2::CAP_DEVICE_CAPS != 0 {
+            cap.device_caps
+        } else {
+            cap.capabilities
+        };
+
+        Ok(Capabilities {
+            driver: buf_to_string(&cap.driver),
+            card: buf_to_string(&cap.card),
+            bus: buf_to_string(&cap.bus_info),
+            version: ((cap.version >> 16 & 0xff) as u8,
+                      (cap.version >> 8 & 0xff) as u8,
+                      (cap.version & 0xff) as u8),
+            flags: CapabilityFlags { bits: flags }
         })
     }
 
+    /// Enumerate the video inputs the device exposes (`VIDIOC_ENUMINPUT`).
+    ///
+    /// Capture cards and tuners expose several: composite, S-Video, tuner, etc.
+    pub fn inputs(&self) -> io::IoResult<Vec<InputInfo>> {
+        let mut res = vec![];
+        let mut input = v4l2::Input::new();
+
+        while try!(v4l2::xioctl_valid(self.fd, v4l2::VIDIOC_ENUMINPUT, &mut input)) {
+            res.push(InputInfo {
+                index: input.index,
+                name: buf_to_string(&input.name),
+                itype: InputType::from_raw(input.itype),
+                standards: input.std
+            });
+
+            input.index += 1;
+        }
+
+        Ok(res)
+    }
+
+    /// Select the active video input by index (`VIDIOC_S_INPUT`).
+    pub fn set_input(&mut self, index: u32) -> io::IoResult<()> {
+        let mut arg = index as i32;
+
+        v4l2::xioctl(self.fd, v4l2::VIDIOC_S_INPUT, &mut arg)
+    }
+
+    /// Enumerate the user controls the device exposes.
+    ///
+    /// Walks every id of the user-control class, skipping controls flagged as
+    /// disabled and ids the driver doesn't implement. For menu controls the
+    /// selectable entries are queried with `VIDIOC_QUERYMENU`. Ids are not
+    /// contiguous (there are gaps in the class), so enumeration continues past
+    /// an invalid id instead of stopping at the first one.
+    pub fn controls(&self) -> io::IoResult<Vec<ControlInfo>> {
+        let mut res = vec![];
+        let mut qctrl = v4l2::QueryCtrl::new();
+
+        for id in range(v4l2::CID_BASE, v4l2::CID_LASTP1) {
+            qctrl.id = id;
+
+            if !try!(v4l2::xioctl_valid(self.fd, v4l2::VIDIOC_QUERYCTRL, &mut qctrl)) {
+                continue;
+            }
+
+            if qctrl.flags & v4l2::CTRL_FLAG_DISABLED != 0 {
+                continue;
+            }
+
+            let ctype = ControlType::from_raw(qctrl.ctype);
+
+            let menu = if ctype == ControlType::Menu || ctype == ControlType::IntegerMenu {
+                try!(self.menu_items(id, ctype, qctrl.minimum, qctrl.maximum))
+            } else {
+                vec![]
+            };
+
+            res.push(ControlInfo {
+                id: id,
+                name: buf_to_string(&qctrl.name),
+                ctype: ctype,
+                minimum: qctrl.minimum,
+                maximum: qctrl.maximum,
+                step: qctrl.step,
+                default: qctrl.default_value,
+                menu: menu
+            });
+        }
+
+        Ok(res)
+    }
+
+    fn menu_items(&self, id: u32, ctype: ControlType, min: i32, max: i32)
+                  -> io::IoResult<Vec<MenuItem>> {
+        let mut res = vec![];
+        let mut menu = v4l2::QueryMenu::new();
+        menu.id = id;
+
+        for index in range(min, max + 1) {
+            menu.index = index as u32;
+
+            if try!(v4l2::xioctl_valid(self.fd, v4l2::VIDIOC_QUERYMENU, &mut menu)) {
+                // The union holds a label for `Menu` but an `__s64` for
+                // `IntegerMenu`; decode only the variant that applies.
+                let (name, value) = if ctype == ControlType::IntegerMenu {
+                    (String::new(), menu.value())
+                } else {
+                    (buf_to_string(&menu.name), 0)
+                };
+
+                res.push(MenuItem {
+                    index: menu.index,
+                    name: name,
+                    value: value
+                });
+            }
+        }
+
+        Ok(res)
+    }
+
+    /// Get the current value of the control with the given id (`VIDIOC_G_CTRL`).
+    pub fn get_control(&self, id: u32) -> io::IoResult<i32> {
+        let mut ctrl = v4l2::Control::new();
+        ctrl.id = id;
+
+        try!(v4l2::xioctl(self.fd, v4l2::VIDIOC_G_CTRL, &mut ctrl));
+
+        Ok(ctrl.value)
+    }
+
+    /// Set the value of the control with the given id (`VIDIOC_S_CTRL`).
+    pub fn set_control(&mut self, id: u32, value: i32) -> io::IoResult<()> {
+        let mut ctrl = v4l2::Control::new();
+        ctrl.id = id;
+        ctrl.value = value;
+
+        v4l2::xioctl(self.fd, v4l2::VIDIOC_S_CTRL, &mut ctrl)
+    }
+
     /// Get detailed info about the available formats.
     pub fn formats(&self) -> io::IoResult<Vec<FormatInfo>> {
         let mut res = vec![];
@@ -218,22 +679,44 @@ impl<'a> Camera<'a> {
 
             // Get modes.
             while try!(v4l2::xioctl_valid(self.fd, v4l2::VIDIOC_ENUM_FRAMESIZES, &mut size)) {
-                if size.ftype != v4l2::FRMSIZE_TYPE_DISCRETE {
-                    size.index += 1;
-                    continue;
-                }
+                let resolution = if size.ftype == v4l2::FRMSIZE_TYPE_DISCRETE {
+                    ResolutionInfo::Discrete(size.discrete.width, size.discrete.height)
+                } else {
+                    // Continuous is stepwise with a step of 1.
+                    ResolutionInfo::Stepwise {
+                        min_width: size.stepwise.min_width,
+                        max_width: size.stepwise.max_width,
+                        step_width: size.stepwise.step_width,
+                        min_height: size.stepwise.min_height,
+                        max_height: size.stepwise.max_height,
+                        step_height: size.stepwise.step_height
+                    }
+                };
+
+                let mut mode = ModeInfo::new(resolution);
 
-                let mut mode = ModeInfo::new((size.discrete.width, size.discrete.height));
+                // Intervals can only be enumerated for a concrete resolution.
+                let (width, height) = match mode.resolution {
+                    ResolutionInfo::Discrete(w, h) => (w, h),
+                    ResolutionInfo::Stepwise { min_width: w, min_height: h, .. } => (w, h)
+                };
 
                 ival.index = 0;
-                ival.width = mode.resolution.0;
-                ival.height = mode.resolution.1;
+                ival.width = width;
+                ival.height = height;
 
                 // Get intervals.
                 while try!(v4l2::xioctl_valid(self.fd, v4l2::VIDIOC_ENUM_FRAMEINTERVALS,
                                               &mut ival)) {
                     if ival.ftype == v4l2::FRMIVAL_TYPE_DISCRET {
-                        mode.intervals.push((ival.discrete.numerator, ival.discrete.denominator));
+                        mode.intervals.push(IntervalInfo::Discrete(ival.discrete.numerator,
+                                                                   ival.discrete.denominator));
+                    } else {
+                        mode.intervals.push(IntervalInfo::Stepwise {
+                            min: (ival.stepwise.min.numerator, ival.stepwise.min.denominator),
+                            max: (ival.stepwise.max.numerator, ival.stepwise.max.denominator),
+                            step: (ival.stepwise.step.numerator, ival.stepwise.step.denominator)
+                        });
                     }
 
                     ival.index += 1;
@@ -259,7 +742,67 @@ impl<'a> Camera<'a> {
     pub fn start(&mut self, config: &Config) -> Result<(), Error> {
         assert_eq!(self.state, State::Idle);
 
-        try!(self.tune_format(config.resolution, config.format, config.field));
+        try!(self.check_mode(config.resolution, config.format, config.interval));
+
+        try!(v4l2::set_nonblocking(self.fd, config.nonblocking));
+        try!(self.tune_standard(config.standard));
+
+        let sizeimage = try!(self.tune_format(config.resolution, config.format, config.field));
+        try!(self.tune_stream(config.interval));
+
+        match config.io {
+            IoMethod::Mmap => {
+                try!(self.alloc_buffers(config.nbuffers));
+
+                if let Err(err) = self.streamon() {
+                    let _ = self.free_buffers();
+                    return Err(Error::Io(err));
+                }
+            }
+            IoMethod::Read => {
+                self.readbuf = Vec::from_elem(sizeimage as uint, 0u8);
+            }
+        }
+
+        self.io = config.io;
+        self.resolution = config.resolution;
+        self.format = [config.format[0], config.format[1], config.format[2], config.format[3]];
+
+        self.state = State::Streaming;
+
+        Ok(())
+    }
+
+    /**
+     * Start streaming, decoding each frame into `config.format` with
+     * libv4lconvert even when the hardware cannot deliver it natively.
+     *
+     * The converter picks the closest source format the driver supports; on
+     * each `capture()` the raw buffer is converted into an owned buffer and the
+     * returned `Frame` reports the requested output FourCC.
+     *
+     * # Panics
+     * if recalled or called after `stop()`.
+     */
+    pub fn start_converted(&mut self, config: &Config) -> Result<(), Error> {
+        assert_eq!(self.state, State::Idle);
+
+        if config.format.len() != 4 {
+            return Err(Error::BadFormat);
+        }
+
+        try!(v4l2::set_nonblocking(self.fd, config.nonblocking));
+        try!(self.tune_standard(config.standard));
+
+        let fourcc = FormatInfo::fourcc(config.format);
+        let mut dest = v4l2::Format::new(config.resolution, fourcc, config.field as u32);
+
+        let converter = try!(v4l2::Converter::new(self.fd));
+        let mut src = try!(converter.try_format(&mut dest));
+
+        // Apply the source format the converter chose.
+        try!(v4l2::xioctl(self.fd, v4l2::VIDIOC_S_FMT, &mut src));
+
         try!(self.tune_stream(config.interval));
         try!(self.alloc_buffers(config.nbuffers));
 
@@ -268,36 +811,101 @@ impl<'a> Camera<'a> {
             return Err(Error::Io(err));
         }
 
+        self.io = IoMethod::Mmap;
         self.resolution = config.resolution;
         self.format = [config.format[0], config.format[1], config.format[2], config.format[3]];
+        let destsize = dest.fmt.sizeimage as uint;
+        self.conv = Some(Conversion {
+            converter: converter,
+            src: src,
+            dest: dest,
+            buffer: Vec::from_elem(destsize, 0u8)
+        });
 
         self.state = State::Streaming;
 
         Ok(())
     }
 
+    /**
+     * Wait until a frame is ready or `timeout_ms` milliseconds elapse, running
+     * `poll()` on the device fd. A negative timeout blocks indefinitely.
+     * Returns whether a frame is ready to `capture()`.
+     *
+     * Most useful together with `Config::nonblocking`, to multiplex several
+     * cameras from a single thread.
+     *
+     * # Panics
+     * If called w/o streaming.
+     */
+    pub fn poll(&self, timeout_ms: i32) -> io::IoResult<bool> {
+        assert_eq!(self.state, State::Streaming);
+
+        v4l2::poll_ready(self.fd, timeout_ms)
+    }
+
     /**
      * Blocking request of frame.
      * It dequeues buffer from a driver, which will be enqueueed after destructing `Frame`.
      *
+     * In read mode (`IoMethod::Read`) it instead `read()`s the frame into a
+     * buffer owned by the camera; nothing is dequeued or requeued.
+     *
+     * In non-blocking mode it returns an error of kind `ResourceUnavailable`
+     * when no frame is ready yet, rather than blocking.
+     *
      * # Panics
      * If called w/o streaming.
      */
     pub fn capture(&self) -> io::IoResult<Frame> {
         assert_eq!(self.state, State::Streaming);
 
-        let mut buf = v4l2::Buffer::new();
+        if let Some(ref conv) = self.conv {
+            let mut buf = v4l2::Buffer::new();
+
+            try!(v4l2::xioctl(self.fd, v4l2::VIDIOC_DQBUF, &mut buf));
+            assert!(buf.index < self.buffers.len() as u32);
 
-        try!(v4l2::xioctl(self.fd, v4l2::VIDIOC_DQBUF, &mut buf));
-        assert!(buf.index < self.buffers.len() as u32);
+            let raw = self.buffers[buf.index as uint][0..buf.bytesused as uint];
+            let n = try!(conv.converter.convert(&conv.src, &conv.dest, raw,
+                                                conv.buffer.as_slice()));
 
-        Ok(Frame {
-            data: self.buffers[buf.index as uint][0..buf.bytesused as uint],
-            resolution: self.resolution,
-            format: self.format,
-            fd: self.fd,
-            buffer: buf
-        })
+            return Ok(Frame {
+                data: conv.buffer[0..n],
+                resolution: self.resolution,
+                format: self.format,
+                fd: self.fd,
+                release: Release::Queue(buf)
+            });
+        }
+
+        match self.io {
+            IoMethod::Mmap => {
+                let mut buf = v4l2::Buffer::new();
+
+                try!(v4l2::xioctl(self.fd, v4l2::VIDIOC_DQBUF, &mut buf));
+                assert!(buf.index < self.buffers.len() as u32);
+
+                Ok(Frame {
+                    data: self.buffers[buf.index as uint][0..buf.bytesused as uint],
+                    resolution: self.resolution,
+                    format: self.format,
+                    fd: self.fd,
+                    release: Release::Queue(buf)
+                })
+            }
+            IoMethod::Read => {
+                let n = try!(v4l2::read(self.fd, self.readbuf.as_slice()));
+
+                Ok(Frame {
+                    data: self.readbuf[0..n],
+                    resolution: self.resolution,
+                    format: self.format,
+                    fd: self.fd,
+                    release: Release::Nothing
+                })
+            }
+        }
     }
 
     /**
@@ -309,15 +917,71 @@ impl<'a> Camera<'a> {
     pub fn stop(&mut self) -> io::IoResult<()> {
         assert_eq!(self.state, State::Streaming);
 
-        try!(self.streamoff());
-        try!(self.free_buffers());
+        match self.io {
+            IoMethod::Mmap => {
+                try!(self.streamoff());
+                try!(self.free_buffers());
+            }
+            IoMethod::Read => {
+                self.readbuf = vec![];
+            }
+        }
 
+        self.conv = None;
         self.state = State::Aborted;
 
         Ok(())
     }
 
-    fn tune_format(&self, resol: (u32, u32), format: &[u8], field: Field) -> Result<(), Error> {
+    /// Validate a requested format/resolution/interval against the enumerated
+    /// grid from `formats()`, so `BadResolution`/`BadInterval` can be reported
+    /// before issuing `S_FMT`/`S_PARM` to the driver.
+    fn check_mode(&self, resol: (u32, u32), format: &[u8], interval: (u32, u32))
+                  -> Result<(), Error> {
+        if format.len() != 4 {
+            return Err(Error::BadFormat);
+        }
+
+        let fourcc = FormatInfo::fourcc(format);
+
+        let formats = try!(self.formats());
+
+        // A driver that doesn't implement `ENUM_FMT` reports no formats; don't
+        // reject a config it would otherwise accept — let `S_FMT` decide.
+        if formats.is_empty() {
+            return Ok(());
+        }
+
+        let info = match formats.iter().find(|f| FormatInfo::fourcc(f.format.as_slice()) == fourcc) {
+            Some(info) => info,
+            None => return Err(Error::BadFormat)
+        };
+
+        // Likewise skip the resolution grid when no frame sizes were enumerated.
+        if info.modes.is_empty() {
+            return Ok(());
+        }
+
+        // The resolution must fall on the grid of at least one mode.
+        let modes: Vec<&ModeInfo> = info.modes.iter()
+            .filter(|m| m.contains_resolution(resol.0, resol.1))
+            .collect();
+
+        if modes.is_empty() {
+            return Err(Error::BadResolution);
+        }
+
+        // Check the interval only against modes that enumerated one; modes
+        // without interval info (or a device that reports none) can't be.
+        let checkable = modes.iter().any(|m| !m.intervals.is_empty());
+        if checkable && !modes.iter().any(|m| m.contains_interval(interval)) {
+            return Err(Error::BadInterval);
+        }
+
+        Ok(())
+    }
+
+    fn tune_format(&self, resol: (u32, u32), format: &[u8], field: Field) -> Result<u32, Error> {
         if format.len() != 4 {
             return Err(Error::BadFormat);
         }
@@ -339,6 +1003,17 @@ impl<'a> Camera<'a> {
             return Err(Error::BadField);
         }
 
+        Ok(fmt.fmt.sizeimage)
+    }
+
+    fn tune_standard(&self, standard: u64) -> Result<(), Error> {
+        if standard == 0 {
+            return Ok(());
+        }
+
+        let mut std = standard;
+        try!(v4l2::xioctl(self.fd, v4l2::VIDIOC_S_STD, &mut std));
+
         Ok(())
     }
 
@@ -424,3 +1099,90 @@ impl<'a> Drop for Camera<'a> {
 pub fn new(device: &str) -> io::IoResult<Camera> {
     Camera::new(device)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{on_grid, ModeInfo, ResolutionInfo, IntervalInfo};
+
+    #[test]
+    fn on_grid_bounds_and_step() {
+        // Below min and above max are off the grid.
+        assert!(!on_grid(99, 100, 200, 10));
+        assert!(!on_grid(201, 100, 200, 10));
+
+        // Bounds are inclusive.
+        assert!(on_grid(100, 100, 200, 10));
+        assert!(on_grid(200, 100, 200, 10));
+
+        // In range but not on a step boundary.
+        assert!(on_grid(110, 100, 200, 10));
+        assert!(!on_grid(115, 100, 200, 10));
+    }
+
+    #[test]
+    fn on_grid_zero_step() {
+        // A zero step accepts any value within [min, max].
+        assert!(on_grid(150, 100, 200, 0));
+        assert!(!on_grid(50, 100, 200, 0));
+    }
+
+    #[test]
+    fn contains_resolution_discrete() {
+        let mode = ModeInfo::new(ResolutionInfo::Discrete(640, 480));
+
+        assert!(mode.contains_resolution(640, 480));
+        assert!(!mode.contains_resolution(641, 480));
+        assert!(!mode.contains_resolution(640, 481));
+    }
+
+    #[test]
+    fn contains_resolution_stepwise() {
+        let mode = ModeInfo::new(ResolutionInfo::Stepwise {
+            min_width: 320, max_width: 640, step_width: 16,
+            min_height: 240, max_height: 480, step_height: 8
+        });
+
+        assert!(mode.contains_resolution(320, 240));
+        assert!(mode.contains_resolution(640, 480));
+        assert!(mode.contains_resolution(336, 248));
+
+        // Off the width step.
+        assert!(!mode.contains_resolution(330, 248));
+        // Out of the height range.
+        assert!(!mode.contains_resolution(336, 488));
+    }
+
+    #[test]
+    fn contains_interval_discrete_and_stepwise() {
+        let mut mode = ModeInfo::new(ResolutionInfo::Discrete(640, 480));
+        mode.intervals.push(IntervalInfo::Discrete(1, 30));
+        mode.intervals.push(IntervalInfo::Stepwise {
+            // 1/30s (fastest) up to 5/30 == 1/6s (slowest), over a common denominator.
+            min: (1, 30), max: (5, 30), step: (1, 30)
+        });
+
+        // On the bounds and strictly between them (compared as rationals).
+        assert!(mode.contains_interval((1, 30)));
+        assert!(mode.contains_interval((5, 30)));
+        assert!(mode.contains_interval((3, 30)));
+        // 2/25 == 0.08 lies between 1/30 and 1/6, even though 25 is off both
+        // numerator and denominator grids.
+        assert!(mode.contains_interval((2, 25)));
+
+        // Outside the rational range on either end.
+        assert!(!mode.contains_interval((1, 60)));
+        assert!(!mode.contains_interval((6, 30)));
+    }
+
+    #[test]
+    fn contains_interval_bounds_unordered() {
+        // The helper must tolerate min/max given in either numeric order.
+        let mut mode = ModeInfo::new(ResolutionInfo::Discrete(640, 480));
+        mode.intervals.push(IntervalInfo::Stepwise {
+            min: (1, 5), max: (1, 30), step: (1, 30)
+        });
+
+        assert!(mode.contains_interval((1, 10)));
+        assert!(!mode.contains_interval((1, 2)));
+    }
+}
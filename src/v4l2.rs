@@ -0,0 +1,263 @@
+use std::{io, mem};
+use libc;
+
+pub const VIDIOC_QUERYCAP: libc::c_ulong = 0x80685600;
+
+const POLLIN:  libc::c_short = 0x0001;
+const POLLPRI: libc::c_short = 0x0002;
+
+#[repr(C)]
+struct PollFd {
+    fd: libc::c_int,
+    events: libc::c_short,
+    revents: libc::c_short
+}
+
+extern {
+    fn poll(fds: *mut PollFd, nfds: libc::c_ulong, timeout: libc::c_int) -> libc::c_int;
+}
+
+/// Toggle the `O_NONBLOCK` flag on the device fd.
+pub fn set_nonblocking(fd: int, nonblocking: bool) -> io::IoResult<()> {
+    let flags = unsafe { libc::fcntl(fd as libc::c_int, libc::F_GETFL, 0) };
+    if flags < 0 {
+        return Err(io::IoError::last_error());
+    }
+
+    let flags = if nonblocking {
+        flags | libc::O_NONBLOCK
+    } else {
+        flags & !libc::O_NONBLOCK
+    };
+
+    if unsafe { libc::fcntl(fd as libc::c_int, libc::F_SETFL, flags) } < 0 {
+        Err(io::IoError::last_error())
+    } else {
+        Ok(())
+    }
+}
+
+/// Wait until the fd has a frame ready or `timeout_ms` elapses. A negative
+/// timeout blocks indefinitely. Returns whether a frame became available.
+pub fn poll_ready(fd: int, timeout_ms: i32) -> io::IoResult<bool> {
+    let mut fds = PollFd {
+        fd: fd as libc::c_int,
+        events: POLLIN | POLLPRI,
+        revents: 0
+    };
+
+    let res = unsafe { poll(&mut fds as *mut PollFd, 1, timeout_ms as libc::c_int) };
+
+    if res < 0 {
+        Err(io::IoError::last_error())
+    } else {
+        Ok(res > 0)
+    }
+}
+
+/// Blocking `read()` of a frame into `buf`, returning the number of bytes read.
+pub fn read(fd: int, buf: &[u8]) -> io::IoResult<uint> {
+    let n = unsafe {
+        libc::read(fd as libc::c_int, buf.as_ptr() as *mut libc::c_void, buf.len() as libc::size_t)
+    };
+
+    if n < 0 {
+        Err(io::IoError::last_error())
+    } else {
+        Ok(n as uint)
+    }
+}
+
+pub const CAP_VIDEO_CAPTURE: u32 = 0x00000001;
+pub const CAP_VIDEO_OUTPUT:  u32 = 0x00000002;
+pub const CAP_VIDEO_OVERLAY: u32 = 0x00000004;
+pub const CAP_READWRITE:     u32 = 0x01000000;
+pub const CAP_STREAMING:     u32 = 0x04000000;
+pub const CAP_DEVICE_CAPS:   u32 = 0x80000000;
+
+pub const VIDIOC_ENUMINPUT: libc::c_ulong = 0xc050561a;
+pub const VIDIOC_S_INPUT:   libc::c_ulong = 0xc0045627;
+pub const VIDIOC_S_STD:     libc::c_ulong = 0x40085618;
+
+pub const INPUT_TYPE_TUNER:  u32 = 1;
+pub const INPUT_TYPE_CAMERA: u32 = 2;
+
+pub const VIDIOC_QUERYCTRL: libc::c_ulong = 0xc0445624;
+pub const VIDIOC_G_CTRL:    libc::c_ulong = 0xc008561b;
+pub const VIDIOC_S_CTRL:    libc::c_ulong = 0xc008561c;
+pub const VIDIOC_QUERYMENU: libc::c_ulong = 0xc02c5625;
+
+/// First id of the user-control class; enumeration starts here.
+pub const CID_BASE: u32 = 0x00980900;
+/// One past the last id of the user-control class.
+pub const CID_LASTP1: u32 = CID_BASE + 44;
+
+pub const CTRL_TYPE_INTEGER:      u32 = 1;
+pub const CTRL_TYPE_BOOLEAN:      u32 = 2;
+pub const CTRL_TYPE_MENU:         u32 = 3;
+pub const CTRL_TYPE_BUTTON:       u32 = 4;
+pub const CTRL_TYPE_INTEGER64:    u32 = 5;
+pub const CTRL_TYPE_CTRL_CLASS:   u32 = 6;
+pub const CTRL_TYPE_STRING:       u32 = 7;
+pub const CTRL_TYPE_BITMASK:      u32 = 8;
+pub const CTRL_TYPE_INTEGER_MENU: u32 = 9;
+
+pub const CTRL_FLAG_DISABLED: u32 = 0x0001;
+
+#[repr(C)]
+struct V4lconvertData;
+
+#[link(name = "v4lconvert")]
+extern {
+    fn v4lconvert_create(fd: libc::c_int) -> *mut V4lconvertData;
+    fn v4lconvert_destroy(data: *mut V4lconvertData);
+    fn v4lconvert_try_format(data: *mut V4lconvertData, dest_fmt: *mut Format,
+                             src_fmt: *mut Format) -> libc::c_int;
+    fn v4lconvert_convert(data: *mut V4lconvertData, src_fmt: *const Format,
+                          dest_fmt: *const Format, src: *const u8, src_size: libc::c_int,
+                          dest: *mut u8, dest_size: libc::c_int) -> libc::c_int;
+}
+
+/// A libv4lconvert handle tied to a device fd.
+pub struct Converter {
+    data: *mut V4lconvertData
+}
+
+impl Converter {
+    pub fn new(fd: int) -> io::IoResult<Converter> {
+        let data = unsafe { v4lconvert_create(fd as libc::c_int) };
+
+        if data.is_null() {
+            Err(io::IoError::last_error())
+        } else {
+            Ok(Converter { data: data })
+        }
+    }
+
+    /// Given the desired output format, return the closest source format the
+    /// driver can deliver that the converter knows how to decode.
+    pub fn try_format(&self, dest: &mut Format) -> io::IoResult<Format> {
+        let mut src: Format = unsafe { mem::zeroed() };
+
+        let res = unsafe {
+            v4lconvert_try_format(self.data, dest as *mut Format, &mut src as *mut Format)
+        };
+
+        if res == -1 {
+            Err(io::IoError::last_error())
+        } else {
+            Ok(src)
+        }
+    }
+
+    /// Convert one raw frame in `src` (of format `src_fmt`) into `dest`
+    /// (of format `dest_fmt`), returning the number of bytes written.
+    pub fn convert(&self, src_fmt: &Format, dest_fmt: &Format, src: &[u8], dest: &[u8])
+                   -> io::IoResult<uint> {
+        let n = unsafe {
+            v4lconvert_convert(self.data,
+                               src_fmt as *const Format, dest_fmt as *const Format,
+                               src.as_ptr(), src.len() as libc::c_int,
+                               dest.as_ptr() as *mut u8, dest.len() as libc::c_int)
+        };
+
+        if n < 0 {
+            Err(io::IoError::last_error())
+        } else {
+            Ok(n as uint)
+        }
+    }
+}
+
+impl Drop for Converter {
+    fn drop(&mut self) {
+        unsafe { v4lconvert_destroy(self.data); }
+    }
+}
+
+#[repr(C)]
+pub struct Capability {
+    pub driver: [u8; 16],
+    pub card: [u8; 32],
+    pub bus_info: [u8; 32],
+    pub version: u32,
+    pub capabilities: u32,
+    pub device_caps: u32,
+    pub reserved: [u32; 3]
+}
+
+impl Capability {
+    pub fn new() -> Capability {
+        unsafe { mem::zeroed() }
+    }
+}
+
+#[repr(C)]
+pub struct QueryCtrl {
+    pub id: u32,
+    pub ctype: u32,
+    pub name: [u8; 32],
+    pub minimum: i32,
+    pub maximum: i32,
+    pub step: i32,
+    pub default_value: i32,
+    pub flags: u32,
+    pub reserved: [u32; 2]
+}
+
+impl QueryCtrl {
+    pub fn new() -> QueryCtrl {
+        unsafe { mem::zeroed() }
+    }
+}
+
+#[repr(C)]
+pub struct Control {
+    pub id: u32,
+    pub value: i32
+}
+
+impl Control {
+    pub fn new() -> Control {
+        unsafe { mem::zeroed() }
+    }
+}
+
+#[repr(C)]
+pub struct Input {
+    pub index: u32,
+    pub name: [u8; 32],
+    pub itype: u32,
+    pub audioset: u32,
+    pub tuner: u32,
+    pub std: u64,
+    pub status: u32,
+    pub capabilities: u32,
+    pub reserved: [u32; 3]
+}
+
+impl Input {
+    pub fn new() -> Input {
+        unsafe { mem::zeroed() }
+    }
+}
+
+#[repr(C, packed)]
+pub struct QueryMenu {
+    pub id: u32,
+    pub index: u32,
+    pub name: [u8; 32],
+    pub reserved: u32
+}
+
+impl QueryMenu {
+    pub fn new() -> QueryMenu {
+        unsafe { mem::zeroed() }
+    }
+
+    /// The menu entry as a signed integer. For `INTEGER_MENU` controls the
+    /// union carries an `__s64 value` in place of `name`.
+    pub fn value(&self) -> i64 {
+        unsafe { *(self.name.as_ptr() as *const i64) }
+    }
+}